@@ -0,0 +1,43 @@
+//! Command line argument definitions shared between the binary and the
+//! library's test fixtures.
+
+/// Which categories of branches a run is allowed to touch.
+///
+/// Each flag gates one axis of the classification independently, so a user
+/// can, for example, ask to delete merged branches everywhere but leave
+/// stray/gone ones alone until they've reviewed them manually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeleteFilter {
+    pub merged_local: bool,
+    pub merged_remote: bool,
+    pub stray_local: bool,
+    pub stray_remote: bool,
+}
+
+impl DeleteFilter {
+    /// Every category enabled. Mostly used by tests and `--delete all`.
+    pub fn all() -> Self {
+        DeleteFilter {
+            merged_local: true,
+            merged_remote: true,
+            stray_local: true,
+            stray_remote: true,
+        }
+    }
+
+    /// Nothing enabled. Useful as a base to build a filter up from.
+    pub fn none() -> Self {
+        DeleteFilter {
+            merged_local: false,
+            merged_remote: false,
+            stray_local: false,
+            stray_remote: false,
+        }
+    }
+}
+
+impl Default for DeleteFilter {
+    fn default() -> Self {
+        DeleteFilter::none()
+    }
+}
@@ -0,0 +1,225 @@
+//! Turns a classification ([`MergedOrStray`]) into actual ref deletions,
+//! without letting one branch's failure abort branches that would have
+//! deleted fine.
+//!
+//! Modeled on jujutsu's `FailedRefExport`/`FailedRefExportReason`: instead
+//! of bailing out on the first problem, every branch is attempted and
+//! failures are collected into a typed report the CLI can print a
+//! per-branch summary from.
+
+use anyhow::Result;
+
+use crate::patch_id::PatchIdCache;
+use crate::{
+    is_merged_into_any, is_squash_merged, Config, Git, LocalBranch, MergedOrStray, RemoteBranch,
+    RemoteRefState,
+};
+
+/// Which bucket the classifier placed a local branch in, driving whether
+/// [`delete_local`] re-checks it against the bases or deletes it outright.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalCategory {
+    /// `merged_locals`/`squashed_locals`: the classifier already proved
+    /// these are reachable from (or patch-id equivalent to) a base, so the
+    /// only reason to check again here is in case the ref moved between
+    /// classify and delete.
+    Verified,
+    /// `stray_locals`: the classifier already decided these are *not*
+    /// merged — that vanished-upstream case is the entire point of the
+    /// stray bucket, so re-running the merged check here would just reject
+    /// every stray branch. Delete it the way `git branch -D` would.
+    Stray,
+}
+
+/// Either side of a branch that [`delete`] may have tried to remove.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Branch {
+    Local(LocalBranch),
+    Remote(RemoteBranch),
+}
+
+/// Why a particular branch couldn't be deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureReason {
+    /// It's the currently checked out branch and `Config::detach` is off.
+    CheckedOut,
+    /// It carries commits that aren't reachable from any base, so deleting
+    /// it would lose work (the local equivalent of `git branch -d`
+    /// refusing without `-D`).
+    UnmergedCommits,
+    /// The remote refused the delete push; the message is whatever the
+    /// remote sent back.
+    RemoteRejected(String),
+    /// The local or remote operation failed for lack of permission.
+    PermissionDenied,
+    /// Anything else git2 reported, kept verbatim for diagnostics.
+    Other(String),
+}
+
+/// One branch that [`delete`] couldn't remove, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedDelete {
+    pub branch: Branch,
+    pub reason: FailureReason,
+}
+
+/// Outcome of a [`delete`] run: what actually got removed, and what didn't.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeleteOutcome {
+    pub deleted: Vec<Branch>,
+    pub failed: Vec<FailedDelete>,
+}
+
+impl DeleteOutcome {
+    /// Whether the CLI should exit non-zero: only when something failed,
+    /// never just because there was nothing to delete.
+    pub fn has_failures(&self) -> bool {
+        !self.failed.is_empty()
+    }
+}
+
+/// Delete every branch selected by `plan`, honoring `config.filter` and
+/// `config.detach`, and returning a full report instead of stopping at the
+/// first branch that can't go.
+pub fn delete(git: &Git, plan: &MergedOrStray, config: &Config) -> Result<DeleteOutcome> {
+    let mut outcome = DeleteOutcome::default();
+
+    let current_branch = current_branch_refname(git)?;
+    let resolved_bases = git.resolve_bases(&config.bases)?;
+    let base_oids = git.base_oids(&resolved_bases)?;
+
+    let categorized = plan
+        .merged_locals
+        .iter()
+        .chain(plan.squashed_locals.iter())
+        .filter(|_| config.filter.merged_local)
+        .map(|local| (local, LocalCategory::Verified))
+        .chain(
+            plan.stray_locals
+                .iter()
+                .filter(|_| config.filter.stray_local)
+                .map(|local| (local, LocalCategory::Stray)),
+        );
+
+    for (local, category) in categorized {
+        match delete_local(git, local, config, current_branch.as_deref(), &base_oids, category) {
+            Ok(()) => outcome.deleted.push(Branch::Local(local.clone())),
+            Err(reason) => outcome.failed.push(FailedDelete {
+                branch: Branch::Local(local.clone()),
+                reason,
+            }),
+        }
+    }
+
+    // A `New`-tagged entry merely shares a name with the local branch on
+    // some other remote; it was never actually tracked, so it's surfaced
+    // for explanation only and must never be pushed a delete for.
+    let trackable_remotes = plan
+        .merged_remotes
+        .iter()
+        .filter(|_| config.filter.merged_remote)
+        .chain(plan.stray_remotes.iter().filter(|_| config.filter.stray_remote))
+        .filter(|remote| remote.state == RemoteRefState::Tracking);
+
+    for remote in trackable_remotes {
+        match delete_remote(git, remote) {
+            Ok(()) => outcome.deleted.push(Branch::Remote(remote.clone())),
+            Err(reason) => outcome.failed.push(FailedDelete {
+                branch: Branch::Remote(remote.clone()),
+                reason,
+            }),
+        }
+    }
+
+    Ok(outcome)
+}
+
+fn current_branch_refname(git: &Git) -> Result<Option<String>> {
+    if !git.repo.head_detached().unwrap_or(false) {
+        if let Ok(head) = git.repo.head() {
+            return Ok(head.name().map(str::to_string));
+        }
+    }
+    Ok(None)
+}
+
+fn delete_local(
+    git: &Git,
+    local: &LocalBranch,
+    config: &Config,
+    current_branch: Option<&str>,
+    base_oids: &[git2::Oid],
+    category: LocalCategory,
+) -> Result<(), FailureReason> {
+    if !config.detach && current_branch == Some(local.refname.as_str()) {
+        return Err(FailureReason::CheckedOut);
+    }
+
+    // Stray branches are deliberately *not* re-checked against the bases:
+    // the classifier already confirmed they aren't merged (that's what
+    // makes them stray), so this would always fail and nothing stray could
+    // ever be deleted. They're deleted the way `git branch -D` would.
+    if category == LocalCategory::Verified {
+        let oid = git
+            .repo
+            .refname_to_id(&local.refname)
+            .map_err(classify_git_error)?;
+        if !is_merged_into_any(&git.repo, oid, base_oids).unwrap_or(false)
+            && !is_squash_merged(&git.repo, &mut PatchIdCache::new(), oid, base_oids)
+        {
+            return Err(FailureReason::UnmergedCommits);
+        }
+    }
+
+    // `config.detach` is on (the check above returned otherwise): move HEAD
+    // off the branch so it can be deleted.
+    if current_branch == Some(local.refname.as_str()) {
+        let oid = git
+            .repo
+            .refname_to_id(&local.refname)
+            .map_err(classify_git_error)?;
+        git.repo.set_head_detached(oid).map_err(classify_git_error)?;
+    }
+
+    let short = local.short_name();
+    let mut branch = git
+        .repo
+        .find_branch(short, git2::BranchType::Local)
+        .map_err(classify_git_error)?;
+    branch.delete().map_err(classify_git_error)
+}
+
+fn delete_remote(git: &Git, remote: &RemoteBranch) -> Result<(), FailureReason> {
+    let mut git_remote = git
+        .repo
+        .find_remote(&remote.remote)
+        .map_err(classify_git_error)?;
+
+    let refspec = format!(":{}", remote.refname);
+    let mut push_result: Result<(), String> = Ok(());
+    {
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.push_update_reference(|_refname, status| {
+            if let Some(message) = status {
+                push_result = Err(message.to_string());
+            }
+            Ok(())
+        });
+        let mut options = git2::PushOptions::new();
+        options.remote_callbacks(callbacks);
+
+        git_remote
+            .push(&[refspec.as_str()], Some(&mut options))
+            .map_err(classify_git_error)?;
+    }
+
+    push_result.map_err(FailureReason::RemoteRejected)
+}
+
+fn classify_git_error(err: git2::Error) -> FailureReason {
+    if err.message().to_lowercase().contains("permission denied") {
+        FailureReason::PermissionDenied
+    } else {
+        FailureReason::Other(err.message().to_string())
+    }
+}
@@ -0,0 +1,150 @@
+//! Fetch-and-prune phase that keeps a local clone's remote-tracking refs
+//! honest before the classifier relies on them.
+//!
+//! Modeled on the `do_fetch` flow in `upgit`: try ssh-agent first, then
+//! fall back to whatever credential helper (or plain `~/.git-credentials`)
+//! the user has configured, and always prune refs the remote deleted so a
+//! stale clone doesn't make a deleted-upstream branch look alive.
+//!
+//! One remote being unreachable (auth failure, no network, a remote
+//! pointing at a missing local path) must not sink the whole run: each
+//! remote is attempted independently and failures are collected into a
+//! typed report, the same shape as [`crate::delete::FailedDelete`].
+
+use anyhow::{Context, Result};
+use git2::{AutotagOption, Cred, ErrorClass, FetchOptions, FetchPrune, RemoteCallbacks};
+
+use crate::Git;
+
+/// Object transfer counts for one remote, surfaced so the CLI can print a
+/// `fetched N objects from origin` style summary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FetchStats {
+    pub received_objects: usize,
+    pub indexed_objects: usize,
+}
+
+/// Why fetching a single remote failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchFailureReason {
+    /// None of ssh-agent, the credential helper, or `~/.git-credentials`
+    /// produced credentials the remote accepted.
+    AuthenticationFailed(String),
+    /// The remote couldn't be reached over the network or ssh (DNS,
+    /// connection refused, etc.). A `../path` remote that no longer exists
+    /// on disk fails with a repository/OS error and is reported as
+    /// [`FetchFailureReason::Other`].
+    NetworkError(String),
+    /// Anything else git2 reported, kept verbatim for diagnostics.
+    Other(String),
+}
+
+/// One remote that [`fetch_and_prune_all`] couldn't fetch, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedFetch {
+    pub remote: String,
+    pub reason: FetchFailureReason,
+}
+
+/// Outcome of a [`fetch_and_prune_all`] run: every remote that refreshed
+/// fine, and every remote that didn't, without either side stopping the
+/// other.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchReport {
+    pub succeeded: Vec<(String, FetchStats)>,
+    pub failed: Vec<FailedFetch>,
+}
+
+fn credentials_callback<'a>() -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|url, username_from_url, allowed_types| {
+        if allowed_types.is_ssh_key() {
+            if let Some(username) = username_from_url {
+                if let Ok(cred) = Cred::ssh_key_from_agent(username) {
+                    return Ok(cred);
+                }
+            }
+        }
+        if allowed_types.is_user_pass_plaintext() {
+            if let Ok(cred) = Cred::credential_helper(&git2::Config::open_default()?, url, username_from_url)
+            {
+                return Ok(cred);
+            }
+        }
+        Cred::default()
+    });
+    callbacks
+}
+
+/// Fetch `remote` with pruning enabled, returning the objects transferred.
+fn fetch_and_prune(git: &Git, remote: &str) -> Result<FetchStats, FetchFailureReason> {
+    let mut git_remote = git
+        .repo
+        .find_remote(remote)
+        .map_err(classify_fetch_error)?;
+
+    let refspecs: Vec<String> = git_remote
+        .fetch_refspecs()
+        .map_err(classify_fetch_error)?
+        .iter()
+        .flatten()
+        .map(str::to_string)
+        .collect();
+
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(credentials_callback());
+    options.download_tags(AutotagOption::All);
+    options.prune(FetchPrune::On);
+
+    git_remote
+        .fetch(&refspecs, Some(&mut options), None)
+        .map_err(classify_fetch_error)?;
+
+    let stats = git_remote.stats();
+    Ok(FetchStats {
+        received_objects: stats.received_objects(),
+        indexed_objects: stats.indexed_objects(),
+    })
+}
+
+/// Fetch-and-prune every remote the repository knows about. Each remote is
+/// attempted independently: a failure on one (e.g. `upstream` being
+/// unreachable while `origin` fetches fine) is recorded in
+/// [`FetchReport::failed`] rather than aborting the remaining remotes.
+pub fn fetch_and_prune_all(git: &Git) -> Result<FetchReport> {
+    let remote_names: Vec<String> = git
+        .repo
+        .remotes()
+        .context("listing remotes")?
+        .iter()
+        .flatten()
+        .map(str::to_string)
+        .collect();
+
+    let mut report = FetchReport::default();
+    for remote in remote_names {
+        match fetch_and_prune(git, &remote) {
+            Ok(stats) => report.succeeded.push((remote, stats)),
+            Err(reason) => report.failed.push(FailedFetch { remote, reason }),
+        }
+    }
+    Ok(report)
+}
+
+fn classify_fetch_error(err: git2::Error) -> FetchFailureReason {
+    match err.class() {
+        ErrorClass::Ssh | ErrorClass::Http if is_auth_error(&err) => {
+            FetchFailureReason::AuthenticationFailed(err.message().to_string())
+        }
+        ErrorClass::Net | ErrorClass::Ssh | ErrorClass::Http => {
+            FetchFailureReason::NetworkError(err.message().to_string())
+        }
+        _ => FetchFailureReason::Other(err.message().to_string()),
+    }
+}
+
+fn is_auth_error(err: &git2::Error) -> bool {
+    matches!(err.code(), git2::ErrorCode::Auth)
+        || err.message().to_lowercase().contains("auth")
+        || err.message().to_lowercase().contains("credentials")
+}
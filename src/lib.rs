@@ -0,0 +1,550 @@
+//! Core classification logic for `git-trim`: given a set of base branches,
+//! work out which local and remote-tracking branches are safe to delete
+//! because they've already been merged, and which ones are "stray" because
+//! their upstream vanished without a trace of a merge.
+
+pub mod args;
+pub mod delete;
+pub mod fetch;
+pub mod patch_id;
+pub mod pattern;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use anyhow::{Context, Result};
+use git2::Repository;
+
+use args::DeleteFilter;
+use fetch::{fetch_and_prune_all, FetchReport};
+use patch_id::{is_squash_or_rebase_merged, PatchIdCache};
+use pattern::StringPattern;
+
+/// Build a `HashSet` literal, the way `maplit::hashset!` does.
+///
+/// Kept local (rather than pulling in `maplit`) since it's only used by a
+/// handful of call sites and the test fixtures.
+#[macro_export]
+macro_rules! set {
+    () => {
+        ::std::collections::HashSet::new()
+    };
+    ($($x:expr),+ $(,)?) => {{
+        let mut s = ::std::collections::HashSet::new();
+        $(s.insert($x.into());)+
+        s
+    }};
+}
+
+/// Sentinel accepted in [`Config::bases`] in place of a concrete refname,
+/// asking `git-trim` to resolve the remote's default branch itself.
+pub const DEFAULT_BASE_SENTINEL: &str = "@default";
+
+/// Whether a local branch's `branch.<name>.remote`/`branch.<name>.merge`
+/// config established a real tracking relationship with a remote ref, and
+/// whether that remote ref has ever actually been observed (fetched in).
+/// Mirrors jujutsu's `RemoteRefState` (`op_store::RemoteRef`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RemoteRefState {
+    /// The local branch explicitly tracks this remote ref (or did, and the
+    /// ref has since disappeared upstream).
+    Tracking,
+    /// A remote ref exists with a matching name, but the local branch
+    /// never configured it as its upstream: a coincidence, not a trail.
+    New,
+}
+
+/// A local branch, identified by its full refname (`refs/heads/foo`).
+///
+/// Equality and hashing only consider the refname: `tracking` is
+/// classifier-derived metadata, not part of the branch's identity, so two
+/// `LocalBranch`es naming the same ref compare equal regardless of it.
+#[derive(Debug, Clone)]
+pub struct LocalBranch {
+    pub refname: String,
+    pub tracking: Option<RemoteRefState>,
+}
+
+impl LocalBranch {
+    pub fn new(refname: &str) -> Self {
+        LocalBranch {
+            refname: refname.to_string(),
+            tracking: None,
+        }
+    }
+
+    /// The branch's short name, e.g. `foo` for `refs/heads/foo`.
+    pub fn short_name(&self) -> &str {
+        self.refname.trim_start_matches("refs/heads/")
+    }
+}
+
+impl From<&str> for LocalBranch {
+    fn from(refname: &str) -> Self {
+        LocalBranch::new(refname)
+    }
+}
+
+impl From<String> for LocalBranch {
+    fn from(refname: String) -> Self {
+        LocalBranch::new(&refname)
+    }
+}
+
+impl PartialEq for LocalBranch {
+    fn eq(&self, other: &Self) -> bool {
+        self.refname == other.refname
+    }
+}
+
+impl Eq for LocalBranch {}
+
+impl std::hash::Hash for LocalBranch {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.refname.hash(state);
+    }
+}
+
+/// A branch on a particular remote, e.g. `origin/feature`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RemoteBranch {
+    pub remote: String,
+    pub refname: String,
+    pub state: RemoteRefState,
+}
+
+/// Branches selected by [`get_merged_or_stray`], split by why they were
+/// selected and whether they're local or remote-tracking.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergedOrStray {
+    pub merged_locals: HashSet<LocalBranch>,
+    pub merged_remotes: HashSet<RemoteBranch>,
+    pub stray_locals: HashSet<LocalBranch>,
+    pub stray_remotes: HashSet<RemoteBranch>,
+    /// Merged via [`patch_id::MergeKind::SquashOrRebase`] equivalence
+    /// rather than plain ancestry: every commit unique to the branch has a
+    /// patch-id match on the base, so it's safe to delete even though the
+    /// branch's tip was never fast-forwarded into a base.
+    pub squashed_locals: HashSet<LocalBranch>,
+}
+
+/// Result of [`get_merged_or_stray`]: what should be deleted, and (for
+/// visibility) what was considered but kept.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergedOrStrayResult {
+    pub to_delete: MergedOrStray,
+    /// Per-remote outcome of the optional prefetch phase (empty unless
+    /// `config.fetch_before_scan` was set). A remote failing to fetch is
+    /// reported here rather than aborting classification.
+    pub fetch_report: FetchReport,
+}
+
+/// Same shape as [`MergedOrStray`], but for the "gone" classifier used on
+/// hub-style forks where the local branch name doesn't match the remote's.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergedOrGone {
+    pub merged_locals: HashSet<String>,
+    pub merged_remotes: HashSet<RemoteBranch>,
+    pub gone_locals: HashSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergedOrGoneResult {
+    pub to_delete: MergedOrGone,
+    /// Per-remote outcome of the optional prefetch phase, see
+    /// [`MergedOrStrayResult::fetch_report`].
+    pub fetch_report: FetchReport,
+}
+
+/// User-facing configuration for a trim run.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Patterns matching branches that other branches are compared against
+    /// to decide "merged". A bare entry parses as [`StringPattern::Exact`];
+    /// the [`DEFAULT_BASE_SENTINEL`] `"@default"` is resolved to the
+    /// remote's default branch before classification runs, and a
+    /// [`StringPattern::Glob`]/[`StringPattern::Substring`] entry expands
+    /// to every local branch it matches.
+    pub bases: Vec<StringPattern>,
+    pub protected_branches: HashSet<StringPattern>,
+    pub filter: DeleteFilter,
+    pub detach: bool,
+    /// When set, fetch and prune every remote before classifying, so stray
+    /// detection reflects the upstream's current state instead of whatever
+    /// the last `git fetch` happened to leave behind.
+    pub fetch_before_scan: bool,
+}
+
+/// Thin wrapper around a `git2::Repository` that the classifier operates
+/// on. Kept as a newtype so we have a place to hang helper methods without
+/// reaching for extension traits on a type we don't own.
+pub struct Git {
+    pub repo: Repository,
+}
+
+impl TryFrom<Repository> for Git {
+    type Error = anyhow::Error;
+
+    fn try_from(repo: Repository) -> Result<Self> {
+        Ok(Git { repo })
+    }
+}
+
+impl Git {
+    /// The remotes configured on this repository, in no particular order.
+    fn remotes(&self) -> Result<Vec<String>> {
+        let names = self.repo.remotes().context("listing remotes")?;
+        Ok(names.iter().flatten().map(str::to_string).collect())
+    }
+
+    /// Resolve a remote's default branch by reading its symbolic
+    /// `refs/remotes/<remote>/HEAD`, falling back to asking the remote
+    /// directly (the same two-step lookup `mure`'s `get_default_branch`
+    /// uses) when the local copy hasn't been set up.
+    fn default_branch_of_remote(&self, remote: &str) -> Result<Option<String>> {
+        let head_ref = format!("refs/remotes/{}/HEAD", remote);
+        if let Ok(reference) = self.repo.find_reference(&head_ref) {
+            if let Some(target) = reference.symbolic_target() {
+                let short = target.trim_start_matches(&format!("refs/remotes/{}/", remote));
+                return Ok(Some(format!("refs/heads/{}", short)));
+            }
+        }
+
+        // No local symref yet (e.g. a shallow or just-cloned repo that
+        // never ran `git remote set-head`): ask the remote's own HEAD via
+        // git2's connected transport instead of giving up.
+        let mut git_remote = self.repo.find_remote(remote)?;
+        git_remote.connect(git2::Direction::Fetch)?;
+        let default = git_remote
+            .default_branch()
+            .ok()
+            .and_then(|buf| buf.as_str().map(str::to_string));
+        git_remote.disconnect()?;
+
+        Ok(default.map(|refname| {
+            let short = refname.trim_start_matches("refs/heads/");
+            format!("refs/heads/{}", short)
+        }))
+    }
+
+    /// All local branches' full refnames, used to expand glob/substring
+    /// base patterns into concrete refs.
+    fn local_branch_refnames(&self) -> Result<Vec<String>> {
+        let mut refnames = Vec::new();
+        for branch in self.repo.branches(Some(git2::BranchType::Local))? {
+            let (branch, _) = branch?;
+            if let Some(name) = branch.get().name() {
+                refnames.push(name.to_string());
+            }
+        }
+        Ok(refnames)
+    }
+
+    /// Resolve each already-expanded base refname (see [`Git::resolve_bases`])
+    /// to every oid it could plausibly mean: the local ref, if it happens to
+    /// be fast-forwarded, and every remote's tracking ref for the same
+    /// branch. A merge into a base almost always lands on the remote first —
+    /// the local copy of `develop`/`master` only catches up once someone
+    /// pulls it — so comparing against the local ref alone misses merges
+    /// that already landed upstream.
+    fn base_oids(&self, resolved_bases: &[String]) -> Result<Vec<git2::Oid>> {
+        let remotes = self.remotes()?;
+        let mut oids = Vec::new();
+        for base in resolved_bases {
+            let short = base.trim_start_matches("refs/heads/");
+            let mut candidates = vec![base.clone(), format!("refs/heads/{}", short)];
+            candidates.extend(
+                remotes
+                    .iter()
+                    .map(|remote| format!("refs/remotes/{}/{}", remote, short)),
+            );
+            for candidate in candidates {
+                if let Ok(oid) = self.repo.refname_to_id(&candidate) {
+                    oids.push(oid);
+                }
+            }
+        }
+        Ok(oids)
+    }
+
+    /// Expand each base pattern into concrete refnames: the
+    /// [`DEFAULT_BASE_SENTINEL`] resolves to the remote's default branch,
+    /// an [`StringPattern::Exact`] entry passes through unchanged, and a
+    /// [`StringPattern::Glob`]/[`StringPattern::Substring`] entry expands
+    /// to every local branch it matches.
+    fn resolve_bases(&self, bases: &[StringPattern]) -> Result<Vec<String>> {
+        let mut resolved = Vec::with_capacity(bases.len());
+        let local_refnames = self.local_branch_refnames()?;
+        for base in bases {
+            match base {
+                StringPattern::Exact(name) if name == DEFAULT_BASE_SENTINEL => {
+                    let mut found_any = false;
+                    for remote in self.remotes()? {
+                        if let Some(default) = self.default_branch_of_remote(&remote)? {
+                            resolved.push(default);
+                            found_any = true;
+                        }
+                    }
+                    if !found_any {
+                        anyhow::bail!(
+                            "could not resolve `{}`: no remote exposes a default branch",
+                            DEFAULT_BASE_SENTINEL
+                        );
+                    }
+                }
+                StringPattern::Exact(name) => resolved.push(name.clone()),
+                pattern => {
+                    for refname in &local_refnames {
+                        let short = refname.trim_start_matches("refs/heads/");
+                        if pattern.matches(refname) || pattern.matches(short) {
+                            resolved.push(refname.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+/// Returns true if `branch` is reachable from (i.e. already merged into)
+/// any of `bases`.
+fn is_merged_into_any(repo: &Repository, branch: git2::Oid, bases: &[git2::Oid]) -> Result<bool> {
+    for &base in bases {
+        if branch == base || repo.graph_descendant_of(base, branch)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Classify every local branch (and its upstream, if any) against `config`.
+///
+/// Auto-detected bases (see [`DEFAULT_BASE_SENTINEL`]) are merged into the
+/// effective base set before classification, and are implicitly protected
+/// so `git-trim` never proposes deleting the branch it measures everything
+/// else against.
+pub fn get_merged_or_stray(git: &Git, config: &Config) -> Result<MergedOrStrayResult> {
+    let fetch_report = if config.fetch_before_scan {
+        fetch_and_prune_all(git).context("fetching before scan")?
+    } else {
+        FetchReport::default()
+    };
+
+    let resolved_bases = git.resolve_bases(&config.bases)?;
+    let protected_patterns: HashSet<StringPattern> = config
+        .protected_branches
+        .iter()
+        .cloned()
+        .chain(resolved_bases.iter().cloned().map(StringPattern::Exact))
+        .collect();
+
+    let base_oids = git.base_oids(&resolved_bases)?;
+
+    let mut result = MergedOrStray::default();
+    let mut patch_id_cache = PatchIdCache::new();
+
+    for branch in git.repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let refname = match branch.get().name() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let short = refname.trim_start_matches("refs/heads/");
+        if pattern::any_matches(&protected_patterns, &refname)
+            || pattern::any_matches(&protected_patterns, short)
+        {
+            continue;
+        }
+        let oid = match branch.get().target() {
+            Some(oid) => oid,
+            None => continue,
+        };
+
+        // A real tracking relationship, read straight from
+        // `branch.<name>.remote`/`branch.<name>.merge`, not guessed from a
+        // same-named remote ref. `branch.upstream()` would silently return
+        // nothing once the tracked ref is pruned, which is exactly the
+        // case we need to still recognize as "was tracking".
+        let tracking = configured_tracking(git, short)?;
+
+        if is_merged_into_any(&git.repo, oid, &base_oids)? {
+            let mut local = LocalBranch::new(&refname);
+            local.tracking = tracking.as_ref().map(|_| RemoteRefState::Tracking);
+            result.merged_locals.insert(local);
+
+            if let Some((remote, _)) = &tracking {
+                for remote_branch in discover_same_named_remotes(git, short, remote)? {
+                    result.merged_remotes.insert(remote_branch);
+                }
+            }
+        } else if is_squash_merged(&git.repo, &mut patch_id_cache, oid, &base_oids) {
+            // GitHub/GitLab "squash and merge"/"rebase and merge" rewrite
+            // commits, so the branch was never fast-forwarded into a base
+            // even though its content landed there. `git cherry`-style
+            // patch-id equivalence still recognizes it as merged.
+            let mut local = LocalBranch::new(&refname);
+            local.tracking = tracking.as_ref().map(|_| RemoteRefState::Tracking);
+            result.squashed_locals.insert(local);
+        } else if let Some((remote, merge_refname)) = &tracking {
+            // It was tracking something, and that something is gone now:
+            // genuinely stray. A branch that never tracked anything, or
+            // merely shares a name with an untracked remote ref, is left
+            // alone below.
+            if !remote_ref_exists(git, remote, merge_refname) {
+                let mut local = LocalBranch::new(&refname);
+                local.tracking = Some(RemoteRefState::Tracking);
+                result.stray_locals.insert(local);
+            }
+        }
+    }
+
+    Ok(MergedOrStrayResult {
+        to_delete: result,
+        fetch_report,
+    })
+}
+
+/// Read `branch.<short_name>.remote`/`.merge`, the config pair that
+/// establishes a genuine tracking relationship, as opposed to a local
+/// branch that merely shares a name with some remote ref.
+fn configured_tracking(git: &Git, short_name: &str) -> Result<Option<(String, String)>> {
+    let cfg = git.repo.config()?;
+    let remote = cfg.get_string(&format!("branch.{}.remote", short_name));
+    let merge = cfg.get_string(&format!("branch.{}.merge", short_name));
+    match (remote, merge) {
+        (Ok(remote), Ok(merge)) => Ok(Some((remote, merge))),
+        _ => Ok(None),
+    }
+}
+
+/// Whether `branch_oid` is equivalent, commit-for-commit, to something
+/// already on one of `base_oids` — just rewritten by a squash or rebase
+/// merge. Each base is tried in turn, with the patch-id scan bounded by the
+/// branch's merge-base with that base; the first match wins.
+fn is_squash_merged(
+    repo: &Repository,
+    cache: &mut PatchIdCache,
+    branch_oid: git2::Oid,
+    base_oids: &[git2::Oid],
+) -> bool {
+    base_oids.iter().any(|&base_oid| {
+        repo.merge_base(branch_oid, base_oid)
+            .ok()
+            .map(|merge_base| {
+                is_squash_or_rebase_merged(repo, cache, branch_oid, merge_base, base_oids)
+                    .unwrap_or(false)
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Whether the remote-tracking ref for `(remote, merge_refname)` has
+/// actually been observed (fetched in), i.e. the remote ref state is
+/// [`RemoteRefState::Tracking`] rather than already gone.
+fn remote_ref_exists(git: &Git, remote: &str, merge_refname: &str) -> bool {
+    let short = merge_refname.trim_start_matches("refs/heads/");
+    git.repo
+        .find_reference(&format!("refs/remotes/{}/{}", remote, short))
+        .is_ok()
+}
+
+/// Every remote that happens to expose a ref named `short`, tagged
+/// [`RemoteRefState::Tracking`] for `configured_remote` (the one
+/// `branch.<local>.remote`/`.merge` actually points at) and
+/// [`RemoteRefState::New`] for any other remote that merely shares the
+/// name by coincidence. Only the `Tracking` entry is safe to delete
+/// alongside the local branch; `New` entries are surfaced so callers can
+/// explain why a same-named branch elsewhere was left alone.
+fn discover_same_named_remotes(
+    git: &Git,
+    short: &str,
+    configured_remote: &str,
+) -> Result<Vec<RemoteBranch>> {
+    let mut found = Vec::new();
+    for remote in git.remotes()? {
+        if git
+            .repo
+            .find_reference(&format!("refs/remotes/{}/{}", remote, short))
+            .is_ok()
+        {
+            let state = if remote == configured_remote {
+                RemoteRefState::Tracking
+            } else {
+                RemoteRefState::New
+            };
+            found.push(RemoteBranch {
+                remote,
+                refname: format!("refs/heads/{}", short),
+                state,
+            });
+        }
+    }
+    Ok(found)
+}
+
+/// Like [`get_merged_or_stray`], but for forks where the local branch
+/// tracks a differently-named ref on another remote (the GitHub PR
+/// checkout flow: `refs/pull/N/head` fetched in as a local branch).
+pub fn get_merged_or_gone(git: &Git, config: &Config) -> Result<MergedOrGoneResult> {
+    let fetch_report = if config.fetch_before_scan {
+        fetch_and_prune_all(git).context("fetching before scan")?
+    } else {
+        FetchReport::default()
+    };
+
+    let resolved_bases = git.resolve_bases(&config.bases)?;
+    let protected_patterns: HashSet<StringPattern> = config
+        .protected_branches
+        .iter()
+        .cloned()
+        .chain(resolved_bases.iter().cloned().map(StringPattern::Exact))
+        .collect();
+
+    let base_oids = git.base_oids(&resolved_bases)?;
+
+    let mut result = MergedOrGone::default();
+
+    for branch in git.repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let short_name = match branch.name()? {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if pattern::any_matches(&protected_patterns, &short_name)
+            || pattern::any_matches(&protected_patterns, &format!("refs/heads/{}", short_name))
+        {
+            continue;
+        }
+        let oid = match branch.get().target() {
+            Some(oid) => oid,
+            None => continue,
+        };
+
+        if is_merged_into_any(&git.repo, oid, &base_oids)? {
+            result.merged_locals.insert(short_name.clone());
+
+            // Built straight from `branch.<name>.remote`/`.merge`, not
+            // gated on a local `refs/remotes/<remote>/...` ref existing:
+            // the hub-fork flow (`git fetch ../origin feature:feature`)
+            // records the remote as the literal URL it was fetched from,
+            // which never creates a remote-tracking ref to look up. Only a
+            // branch head (`refs/heads/...`) is offered for a delete push.
+            if let Some((remote, merge)) = configured_tracking(git, &short_name)? {
+                if !merge.starts_with("refs/heads/") {
+                    continue;
+                }
+                result.merged_remotes.insert(RemoteBranch {
+                    remote,
+                    refname: merge,
+                    state: RemoteRefState::Tracking,
+                });
+            }
+        }
+    }
+
+    Ok(MergedOrGoneResult {
+        to_delete: result,
+        fetch_report,
+    })
+}
@@ -0,0 +1,113 @@
+//! Squash/rebase-merge detection via patch-id equivalence — the same
+//! algorithm `git cherry` uses to tell "this commit's diff already exists
+//! somewhere else in the history" apart from "this commit is unreachable".
+//!
+//! GitHub/GitLab's "Squash and merge" and "Rebase and merge" rewrite
+//! commits, so a feature branch's commits never appear verbatim on the
+//! base even though their content landed. Without this, such branches are
+//! classified `stray` instead of `merged` and lose the safety distinction.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use git2::{Oid, Repository};
+
+/// Which path produced a `merged` classification, so the CLI can tell a
+/// user "this looks squash-merged" instead of implying a plain fast-forward.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeKind {
+    /// The branch tip is a direct ancestor of (or equal to) a base.
+    FastForward,
+    /// Every commit unique to the branch has a patch-id match on the base,
+    /// i.e. it was squashed or rebased in rather than merged verbatim.
+    SquashOrRebase,
+}
+
+/// Memoizes `Commit -> patch-id` so scanning many candidate branches
+/// against the same base history doesn't recompute the same diff over and
+/// over.
+#[derive(Default)]
+pub struct PatchIdCache {
+    ids: HashMap<Oid, Oid>,
+}
+
+impl PatchIdCache {
+    pub fn new() -> Self {
+        PatchIdCache::default()
+    }
+
+    /// The patch-id of `commit`: a hash of its diff against its first
+    /// parent (or the empty tree, for a root commit), normalized so
+    /// whitespace-only line-number shifts still compare equal. Merge
+    /// commits have no single meaningful diff and are skipped by the
+    /// caller instead.
+    fn patch_id(&mut self, repo: &Repository, commit: Oid) -> Result<Oid> {
+        if let Some(&id) = self.ids.get(&commit) {
+            return Ok(id);
+        }
+
+        let commit = repo.find_commit(commit)?;
+        let tree = commit.tree()?;
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        let id = diff.patchid(None)?;
+
+        self.ids.insert(commit.id(), id);
+        Ok(id)
+    }
+}
+
+/// Commits unique to `tip` that aren't reachable from `merge_base`, in the
+/// order `git rev-list merge_base..tip` would report them, skipping merge
+/// commits (they have no single patch-id worth comparing).
+fn commits_since(repo: &Repository, tip: Oid, merge_base: Oid) -> Result<Vec<Oid>> {
+    let mut walk = repo.revwalk()?;
+    walk.push(tip)?;
+    walk.hide(merge_base)?;
+
+    let mut commits = Vec::new();
+    for oid in walk {
+        let oid = oid?;
+        let commit = repo.find_commit(oid)?;
+        if commit.parent_count() <= 1 {
+            commits.push(oid);
+        }
+    }
+    Ok(commits)
+}
+
+/// True if every commit unique to `branch_tip` (since `merge_base`) has a
+/// patch-id match among the commits unique to `base_heads` (since the same
+/// `merge_base`) — the `git cherry` equivalence check.
+///
+/// `merge_base` bounds both scans so the base side stays cheap even on a
+/// long-lived repo: we only look back as far as the oldest point the
+/// candidate branch could have diverged.
+pub fn is_squash_or_rebase_merged(
+    repo: &Repository,
+    cache: &mut PatchIdCache,
+    branch_tip: Oid,
+    merge_base: Oid,
+    base_heads: &[Oid],
+) -> Result<bool> {
+    let branch_commits = commits_since(repo, branch_tip, merge_base)?;
+    if branch_commits.is_empty() {
+        return Ok(false);
+    }
+
+    let mut base_patch_ids = HashSet::new();
+    for &base_head in base_heads {
+        for commit in commits_since(repo, base_head, merge_base)? {
+            base_patch_ids.insert(cache.patch_id(repo, commit)?);
+        }
+    }
+
+    for commit in branch_commits {
+        let id = cache.patch_id(repo, commit)?;
+        if !base_patch_ids.contains(&id) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
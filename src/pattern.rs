@@ -0,0 +1,130 @@
+//! A small string-matching pattern type, mirroring jujutsu's
+//! `StringPattern`, used so `protected_branches` and `bases` can match a
+//! whole family of branches (`release/*`) instead of listing each one.
+
+/// A pattern matched against a branch's short name or full refname.
+///
+/// A bare string (via `From<&str>`/`From<String>`) becomes [`Exact`], so
+/// existing `set!{"refs/heads/master"}`-style configs keep meaning exactly
+/// what they used to.
+///
+/// [`Exact`]: StringPattern::Exact
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StringPattern {
+    /// Matches only the exact string.
+    Exact(String),
+    /// Matches path segments with `*` (one segment) and `**` (any number
+    /// of segments), in the style of `.gitignore`/glob path matching.
+    Glob(String),
+    /// Matches if the needle occurs anywhere in the candidate.
+    Substring(String),
+}
+
+impl StringPattern {
+    pub fn matches(&self, candidate: &str) -> bool {
+        match self {
+            StringPattern::Exact(pattern) => pattern == candidate,
+            StringPattern::Glob(pattern) => glob_match(pattern, candidate),
+            StringPattern::Substring(needle) => candidate.contains(needle.as_str()),
+        }
+    }
+}
+
+impl From<&str> for StringPattern {
+    fn from(s: &str) -> Self {
+        StringPattern::Exact(s.to_string())
+    }
+}
+
+impl From<String> for StringPattern {
+    fn from(s: String) -> Self {
+        StringPattern::Exact(s)
+    }
+}
+
+/// Match `candidate` against a `/`-segmented glob `pattern`, where `*`
+/// matches a single segment (no `/`) and `**` matches any number of
+/// segments, including zero.
+fn glob_match(pattern: &str, candidate: &str) -> bool {
+    let pattern_segments: Vec<&str> = pattern.split('/').collect();
+    let candidate_segments: Vec<&str> = candidate.split('/').collect();
+    segments_match(&pattern_segments, &candidate_segments)
+}
+
+fn segments_match(pattern: &[&str], candidate: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => candidate.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=candidate.len()).any(|i| segments_match(rest, &candidate[i..]))
+        }
+        Some((&head, rest)) => match candidate.split_first() {
+            Some((&candidate_head, candidate_rest)) => {
+                segment_match(head, candidate_head) && segments_match(rest, candidate_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match a single path segment, where `*` stands for any run of
+/// characters within the segment.
+fn segment_match(pattern: &str, candidate: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == candidate;
+    }
+
+    let mut rest = candidate;
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            if !rest.starts_with(part) {
+                return false;
+            }
+            rest = &rest[part.len()..];
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else if let Some(pos) = rest.find(part) {
+            rest = &rest[pos + part.len()..];
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
+/// True if any pattern in `patterns` matches `candidate`.
+pub fn any_matches<'a>(patterns: impl IntoIterator<Item = &'a StringPattern>, candidate: &str) -> bool {
+    patterns.into_iter().any(|pattern| pattern.matches(candidate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_matches_only_itself() {
+        let pattern = StringPattern::Exact("refs/heads/master".to_string());
+        assert!(pattern.matches("refs/heads/master"));
+        assert!(!pattern.matches("refs/heads/master2"));
+    }
+
+    #[test]
+    fn glob_star_matches_one_segment() {
+        let pattern = StringPattern::Glob("refs/heads/release/*".to_string());
+        assert!(pattern.matches("refs/heads/release/1.0"));
+        assert!(!pattern.matches("refs/heads/release/1.0/hotfix"));
+    }
+
+    #[test]
+    fn glob_double_star_matches_any_depth() {
+        let pattern = StringPattern::Glob("refs/heads/release/**".to_string());
+        assert!(pattern.matches("refs/heads/release/1.0/hotfix"));
+        assert!(pattern.matches("refs/heads/release"));
+    }
+
+    #[test]
+    fn bare_string_parses_as_exact() {
+        let pattern: StringPattern = "refs/heads/master".into();
+        assert_eq!(pattern, StringPattern::Exact("refs/heads/master".to_string()));
+    }
+}
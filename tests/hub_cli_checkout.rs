@@ -5,7 +5,7 @@ use std::convert::TryFrom;
 use anyhow::Result;
 use git2::Repository;
 
-use git_trim::{get_merged_or_gone, Config, Git, MergedOrGone, RemoteBranch};
+use git_trim::{get_merged_or_gone, Config, Git, MergedOrGone, RemoteBranch, RemoteRefState};
 
 use fixture::{rc, Fixture};
 use git_trim::args::DeleteFilter;
@@ -49,12 +49,13 @@ fn fixture() -> Fixture {
     )
 }
 
-fn config() -> Config<'static> {
+fn config() -> Config {
     Config {
-        bases: vec!["master"],
+        bases: vec!["master".into()],
         protected_branches: set! {},
         filter: DeleteFilter::all(),
         detach: true,
+        fetch_before_scan: false,
     }
 }
 
@@ -116,6 +117,7 @@ fn test_accepted_but_forgot_to_delete() -> Result<()> {
                 RemoteBranch {
                     remote: "../origin".to_string(),
                     refname: "refs/heads/feature".to_string(),
+                    state: RemoteRefState::Tracking,
                 },
             },
             ..Default::default()
@@ -5,7 +5,7 @@ use std::convert::TryFrom;
 use anyhow::Result;
 use git2::Repository;
 
-use git_trim::{get_merged_or_stray, Config, Git, LocalBranch, MergedOrStray, RemoteBranch};
+use git_trim::{get_merged_or_stray, Config, Git, LocalBranch, MergedOrStray, RemoteBranch, RemoteRefState};
 
 use fixture::{rc, Fixture};
 use git_trim::args::DeleteFilter;
@@ -36,12 +36,13 @@ fn fixture() -> Fixture {
     )
 }
 
-fn config() -> Config<'static> {
+fn config() -> Config {
     Config {
-        bases: vec!["refs/heads/develop", "refs/heads/master"],
+        bases: vec!["refs/heads/develop".into(), "refs/heads/master".into()],
         protected_branches: set! {},
         filter: DeleteFilter::all(),
         detach: true,
+        fetch_before_scan: false,
     }
 }
 
@@ -114,6 +115,7 @@ fn test_feature_to_develop_but_forgot_to_delete() -> Result<()> {
                 RemoteBranch {
                     remote: "origin".to_string(),
                     refname: "refs/heads/feature".to_string(),
+                    state: RemoteRefState::Tracking,
                 },
             },
             ..Default::default()
@@ -197,6 +199,7 @@ fn test_develop_to_master_but_forgot_to_delete() -> Result<()> {
                 RemoteBranch {
                     remote: "origin".to_string(),
                     refname: "refs/heads/feature".to_string(),
+                    state: RemoteRefState::Tracking,
                 },
             },
             ..Default::default()
@@ -278,6 +281,7 @@ fn test_hotfix_to_master_forgot_to_delete() -> Result<()> {
                 RemoteBranch {
                     remote: "origin".to_string(),
                     refname: "refs/heads/hotfix".to_string(),
+                    state: RemoteRefState::Tracking,
                 },
             },
             ..Default::default()